@@ -0,0 +1,90 @@
+use std::error::Error;
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use wincms::cert::{CertContext, CertStore};
+
+pub enum CertId {
+    Subject(String),
+    Thumbprint(Vec<u8>),
+}
+
+impl CertId {
+    pub fn parse(id: &str) -> Result<CertId, Box<dyn Error>> {
+        if let Some(hex) = id
+            .strip_prefix("sha1:")
+            .or_else(|| id.strip_prefix("sha256:"))
+        {
+            Ok(CertId::Thumbprint(decode_hex(hex)?))
+        } else {
+            Ok(CertId::Subject(id.to_owned()))
+        }
+    }
+
+    pub fn find(&self, store: &CertStore) -> Result<Vec<CertContext>, Box<dyn Error>> {
+        match self {
+            CertId::Subject(subject) => Ok(store.find_cert_by_subject_str(subject)?),
+            CertId::Thumbprint(digest) => Ok(store
+                .certs()?
+                .into_iter()
+                .filter(|cert| thumbprint(cert, digest.len()) == *digest)
+                .collect()),
+        }
+    }
+}
+
+pub fn thumbprint(cert: &CertContext, len: usize) -> Vec<u8> {
+    if len == 20 {
+        Sha1::digest(cert.der()).to_vec()
+    } else {
+        Sha256::digest(cert.der()).to_vec()
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("invalid thumbprint: {}", hex).into());
+    }
+    if hex.len() % 2 != 0 {
+        return Err(format!("invalid thumbprint length: {}", hex).into());
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).map_err(Into::into))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let digest = [0xde, 0xad, 0xbe, 0xef];
+        let hex = hex::encode(digest);
+        assert_eq!(hex, "deadbeef");
+        assert_eq!(decode_hex(&hex).unwrap(), digest);
+    }
+
+    #[test]
+    fn decode_hex_rejects_odd_length() {
+        assert!(decode_hex("abc").is_err());
+    }
+
+    #[test]
+    fn decode_hex_rejects_non_ascii_without_panicking() {
+        assert!(decode_hex("a€").is_err());
+    }
+
+    #[test]
+    fn parse_detects_thumbprint_vs_subject() {
+        assert!(matches!(
+            CertId::parse("sha256:deadbeef").unwrap(),
+            CertId::Thumbprint(_)
+        ));
+        assert!(matches!(
+            CertId::parse("CN=Test").unwrap(),
+            CertId::Subject(_)
+        ));
+    }
+}
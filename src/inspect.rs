@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt;
+
+use cms::cert::{CertificateChoices, CertificateSet};
+use cms::content_info::ContentInfo;
+use cms::enveloped_data::{EnvelopedData, RecipientIdentifier, RecipientInfo};
+use cms::signed_data::{SignedData, SignerIdentifier};
+use const_oid::db::rfc5280::ID_CE_SUBJECT_KEY_IDENTIFIER;
+use const_oid::db::rfc5911::{ID_ENVELOPED_DATA, ID_SIGNED_DATA};
+use der::asn1::OctetString;
+use der::Decode;
+
+pub enum RecipientId {
+    IssuerAndSerial(String, String),
+    SubjectKeyIdentifier(String),
+}
+
+impl fmt::Display for RecipientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipientId::IssuerAndSerial(issuer, serial) => {
+                write!(f, "issuer={} serial={}", issuer, serial)
+            }
+            RecipientId::SubjectKeyIdentifier(ski) => write!(f, "ski={}", ski),
+        }
+    }
+}
+
+pub struct RecipientEntry {
+    pub id: RecipientId,
+}
+
+pub enum SignerSubject {
+    Subject(String),
+    Issuer(String),
+}
+
+impl fmt::Display for SignerSubject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerSubject::Subject(s) => write!(f, "subject={}", s),
+            SignerSubject::Issuer(s) => write!(f, "issuer={}", s),
+        }
+    }
+}
+
+pub struct SignerEntry {
+    pub subject: SignerSubject,
+    pub digest_algorithm: String,
+}
+
+fn find_signer_cert_subject(certs: Option<&CertificateSet>, sid: &SignerIdentifier) -> Option<String> {
+    let certs = certs?;
+    certs.0.iter().find_map(|choice| {
+        let CertificateChoices::Certificate(cert) = choice else {
+            return None;
+        };
+        let tbs = &cert.tbs_certificate;
+        let is_match = match sid {
+            SignerIdentifier::IssuerAndSerialNumber(isn) => {
+                tbs.issuer == isn.issuer && tbs.serial_number == isn.serial_number
+            }
+            SignerIdentifier::SubjectKeyIdentifier(skid) => tbs
+                .extensions
+                .iter()
+                .flatten()
+                .any(|ext| {
+                    ext.extn_id == ID_CE_SUBJECT_KEY_IDENTIFIER
+                        && OctetString::from_der(ext.extn_value.as_bytes())
+                            .map(|ski| ski.as_bytes() == skid.0.as_bytes())
+                            .unwrap_or(false)
+                }),
+        };
+        is_match.then(|| tbs.subject.to_string())
+    })
+}
+
+pub struct CmsInfo {
+    pub content_type: String,
+    pub is_enveloped: bool,
+    pub recipients: Vec<RecipientEntry>,
+    pub content_encryption_algorithm: String,
+    pub signers: Vec<SignerEntry>,
+    pub encrypts_signed_data: bool,
+}
+
+pub fn inspect(der: &[u8]) -> Result<CmsInfo, Box<dyn Error>> {
+    let content_info = ContentInfo::from_der(der)?;
+
+    let mut info = CmsInfo {
+        content_type: content_info.content_type.to_string(),
+        is_enveloped: false,
+        recipients: Vec::new(),
+        content_encryption_algorithm: String::new(),
+        signers: Vec::new(),
+        encrypts_signed_data: false,
+    };
+
+    if content_info.content_type == ID_ENVELOPED_DATA {
+        let enveloped: EnvelopedData = content_info.content.decode_as()?;
+        info.is_enveloped = true;
+        info.content_encryption_algorithm = enveloped
+            .encrypted_content_info
+            .content_enc_alg
+            .oid
+            .to_string();
+        info.encrypts_signed_data =
+            enveloped.encrypted_content_info.content_type == ID_SIGNED_DATA;
+
+        for recipient in enveloped.recip_infos.0.iter() {
+            if let RecipientInfo::Ktri(ktri) = recipient {
+                let id = match &ktri.rid {
+                    RecipientIdentifier::IssuerAndSerialNumber(isn) => {
+                        RecipientId::IssuerAndSerial(isn.issuer.to_string(), isn.serial_number.to_string())
+                    }
+                    RecipientIdentifier::SubjectKeyIdentifier(skid) => {
+                        RecipientId::SubjectKeyIdentifier(hex::encode(skid.0.as_bytes()))
+                    }
+                };
+                info.recipients.push(RecipientEntry { id });
+            }
+        }
+    } else if content_info.content_type == ID_SIGNED_DATA {
+        let signed: SignedData = content_info.content.decode_as()?;
+
+        for signer in signed.signer_infos.0.iter() {
+            let subject = match find_signer_cert_subject(signed.certificates.as_ref(), &signer.sid)
+            {
+                Some(subject) => SignerSubject::Subject(subject),
+                None => SignerSubject::Issuer(match &signer.sid {
+                    SignerIdentifier::IssuerAndSerialNumber(isn) => isn.issuer.to_string(),
+                    SignerIdentifier::SubjectKeyIdentifier(skid) => hex::encode(skid.0.as_bytes()),
+                }),
+            };
+            info.signers.push(SignerEntry {
+                subject,
+                digest_algorithm: signer.digest_alg.oid.to_string(),
+            });
+        }
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNED_ISSUER_SERIAL: &[u8] = include_bytes!("../testdata/signed_issuer_serial.der");
+    const SIGNED_SKI: &[u8] = include_bytes!("../testdata/signed_ski.der");
+    const SIGNED_NO_CERTS: &[u8] = include_bytes!("../testdata/signed_no_certs.der");
+    const ENVELOPED_ISSUER_SERIAL: &[u8] =
+        include_bytes!("../testdata/enveloped_issuer_serial.der");
+    const ENVELOPED_SKI: &[u8] = include_bytes!("../testdata/enveloped_ski.der");
+
+    #[test]
+    fn dispatches_signed_data() {
+        let info = inspect(SIGNED_ISSUER_SERIAL).unwrap();
+        assert!(!info.is_enveloped);
+        assert!(info.recipients.is_empty());
+        assert_eq!(info.signers.len(), 1);
+    }
+
+    #[test]
+    fn dispatches_enveloped_data() {
+        let info = inspect(ENVELOPED_ISSUER_SERIAL).unwrap();
+        assert!(info.is_enveloped);
+        assert!(info.signers.is_empty());
+        assert_eq!(info.recipients.len(), 1);
+    }
+
+    #[test]
+    fn matches_signer_by_issuer_and_serial() {
+        let info = inspect(SIGNED_ISSUER_SERIAL).unwrap();
+        let signer = &info.signers[0];
+        match &signer.subject {
+            SignerSubject::Subject(s) => assert!(s.contains("Test Signer")),
+            SignerSubject::Issuer(_) => panic!("expected embedded cert to match by issuer/serial"),
+        }
+    }
+
+    #[test]
+    fn matches_signer_by_subject_key_identifier() {
+        let info = inspect(SIGNED_SKI).unwrap();
+        let signer = &info.signers[0];
+        match &signer.subject {
+            SignerSubject::Subject(s) => assert!(s.contains("Test Signer")),
+            SignerSubject::Issuer(_) => panic!("expected embedded cert to match by SKI"),
+        }
+    }
+
+    #[test]
+    fn falls_back_to_issuer_when_no_embedded_cert_matches() {
+        let info = inspect(SIGNED_NO_CERTS).unwrap();
+        let signer = &info.signers[0];
+        assert!(matches!(signer.subject, SignerSubject::Issuer(_)));
+    }
+
+    #[test]
+    fn recognizes_subject_key_identifier_recipient() {
+        let info = inspect(ENVELOPED_SKI).unwrap();
+        assert!(info.is_enveloped);
+        assert_eq!(info.recipients.len(), 1);
+        assert!(matches!(
+            info.recipients[0].id,
+            RecipientId::SubjectKeyIdentifier(_)
+        ));
+    }
+}
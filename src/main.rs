@@ -10,9 +10,16 @@ use clap::Parser;
 use log::debug;
 use wincms::{
     cert::{CertContext, CertStore, CertStoreType},
-    cms::CmsContent,
+    cms::{CmsContent, DigestAlgorithm},
 };
 
+mod certid;
+mod compress;
+mod inspect;
+mod pem;
+
+use certid::CertId;
+
 #[derive(Parser)]
 #[clap(
     about = "CMS encoding utility to sign/encrypt or decrypt/verify a CMS-encoded message",
@@ -30,6 +37,22 @@ struct AppParams {
     #[clap(short = 'q', long = "quiet", help = "Disable Windows CSP UI prompts")]
     silent: bool,
 
+    #[clap(
+        short = 'a',
+        long = "armor",
+        global = true,
+        help = "Encode output as PEM (decode: detect and strip automatically)"
+    )]
+    armor: bool,
+
+    #[clap(
+        short = 'z',
+        long = "compress",
+        global = true,
+        help = "Compress and length-pad the plaintext before encrypting it (decode: detect and reverse automatically)"
+    )]
+    compress: bool,
+
     #[clap(
         short = 't',
         long = "store-type",
@@ -70,27 +93,116 @@ struct AppParams {
 enum CmsCommand {
     #[clap(name = "encode", about = "Sign and encrypt data")]
     Encode(CmsEncodeCmd),
-    #[clap(name = "decode", about = "Decrypt and verify data")]
+    #[clap(name = "sign", about = "Sign data without encrypting it")]
+    Sign(CmsSignCmd),
+    #[clap(name = "encrypt", about = "Encrypt data without signing it")]
+    Encrypt(CmsEncryptCmd),
+    #[clap(name = "decode", about = "Decrypt and/or verify data")]
     Decode(CmsDecodeCmd),
+    #[clap(
+        name = "inspect",
+        about = "Show the structure of a CMS message without decrypting it"
+    )]
+    Inspect,
+    #[clap(name = "list", about = "Enumerate certificates available in the store")]
+    List(CmsListCmd),
 }
 
 #[derive(Parser)]
 struct CmsEncodeCmd {
-    #[clap(short = 's', long = "signer", help = "Signer certificate ID")]
+    #[clap(
+        short = 's',
+        long = "signer",
+        help = "Signer certificate ID, subject substring or sha1:/sha256: thumbprint"
+    )]
+    signer: String,
+
+    #[clap(
+        index = 1,
+        required = true,
+        help = "One or more recipient certificate IDs, subject substrings or sha1:/sha256: thumbprints"
+    )]
+    recipients: Vec<String>,
+
+    #[clap(
+        long = "digest",
+        value_enum,
+        default_value = "sha256",
+        help = "Signature digest algorithm"
+    )]
+    digest: DigestAlgorithm,
+
+    #[clap(
+        long = "pss",
+        help = "Sign with RSA-PSS padding instead of PKCS#1 v1.5 (ignored for EC keys)"
+    )]
+    pss: bool,
+}
+
+#[derive(Parser)]
+struct CmsSignCmd {
+    #[clap(
+        short = 's',
+        long = "signer",
+        help = "Signer certificate ID, subject substring or sha1:/sha256: thumbprint"
+    )]
     signer: String,
 
+    #[clap(
+        short = 'd',
+        long = "detached",
+        help = "Produce a detached signature only, leaving the original content untouched"
+    )]
+    detached: bool,
+
+    #[clap(
+        long = "digest",
+        value_enum,
+        default_value = "sha256",
+        help = "Signature digest algorithm"
+    )]
+    digest: DigestAlgorithm,
+
+    #[clap(
+        long = "pss",
+        help = "Sign with RSA-PSS padding instead of PKCS#1 v1.5 (ignored for EC keys)"
+    )]
+    pss: bool,
+}
+
+#[derive(Parser)]
+struct CmsEncryptCmd {
     #[clap(
         index = 1,
         required = true,
-        help = "One or more recipient certificate IDs"
+        help = "One or more recipient certificate IDs, subject substrings or sha1:/sha256: thumbprints"
     )]
     recipients: Vec<String>,
 }
 
+#[derive(Parser)]
+struct CmsListCmd {
+    #[clap(
+        short = 'k',
+        long = "keys",
+        help = "Probe each certificate for an available private key (may prompt/authenticate against smart cards or HSMs)"
+    )]
+    keys: bool,
+}
+
 #[derive(Parser)]
 struct CmsDecodeCmd {
-    #[clap(index = 1, required = true, help = "Recipient certificate ID")]
-    recipient: String,
+    #[clap(
+        index = 1,
+        help = "Recipient certificate ID, subject substring or sha1:/sha256: thumbprint (not needed when verifying a detached signature)"
+    )]
+    recipient: Option<String>,
+
+    #[clap(
+        long = "content",
+        help = "Original content file a detached signature (passed via --in) applies to"
+    )]
+    content_file: Option<PathBuf>,
 }
 
 enum MessageSource {
@@ -115,105 +227,301 @@ fn get_cert_with_key(certs: &mut [CertContext], silent: bool) -> Option<CertCont
         .find_map(|cert| cert.acquire_key(silent).map(|_| cert.clone()).ok())
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: AppParams = AppParams::parse();
+fn acquire_signer(
+    store: &CertStore,
+    signer_id: &str,
+    silent: bool,
+    pin: Option<&str>,
+    using_pfx: bool,
+) -> Result<CertContext, Box<dyn Error>> {
+    let mut signers = CertId::parse(signer_id)?.find(store)?;
+
+    let signer = get_cert_with_key(&mut signers, silent).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("Cannot find signer certificate for {}", signer_id),
+        )
+    })?;
+    debug!("Acquired signer certificate for {}", signer_id);
+
+    let key = signer.key().unwrap();
+    let key_prov = key.get_provider_name()?;
+    let key_name = key.get_name()?;
+    debug!("Acquired private key: {}: {}", key_prov, key_name);
+
+    if !using_pfx {
+        if let Some(pin) = pin {
+            key.set_pin(pin)?;
+            debug!("Pin code set");
+        }
+    }
 
-    env_logger::init();
+    Ok(signer)
+}
+
+fn acquire_recipients(
+    store: &CertStore,
+    recipient_ids: &[String],
+) -> Result<Vec<CertContext>, Box<dyn Error>> {
+    let mut recipients = Vec::new();
+    for rcpt in recipient_ids {
+        recipients.extend(CertId::parse(rcpt)?.find(store)?.into_iter());
+    }
+    debug!("Acquired {} recipient certificate(s)", recipients.len());
+    Ok(recipients)
+}
+
+fn acquire_recipient(
+    store: &CertStore,
+    recipient_id: &str,
+    silent: bool,
+    pin: Option<&str>,
+    using_pfx: bool,
+) -> Result<CertContext, Box<dyn Error>> {
+    let mut recipients = CertId::parse(recipient_id)?.find(store)?;
+
+    let cert = get_cert_with_key(&mut recipients, silent).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("Cannot find recipient certificate for {}", recipient_id),
+        )
+    })?;
+    debug!("Acquired recipient certificate for {}", recipient_id);
+
+    let key = cert.key().unwrap();
+    let key_prov = key.get_provider_name()?;
+    let key_name = key.get_name()?;
+    debug!("Acquired private key: {}: {}", key_prov, key_name);
+
+    if !using_pfx {
+        if let Some(pin) = pin {
+            key.set_pin(pin)?;
+            debug!("Pin code set");
+        }
+    }
+
+    Ok(cert)
+}
 
-    let source = if let Some(input_file) = args.input_file {
+fn write_output(
+    data: &[u8],
+    armor: bool,
+    output_file: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let armored;
+    let data = if armor {
+        armored = pem::armor(data).into_bytes();
+        armored.as_slice()
+    } else {
+        data
+    };
+
+    if let Some(output_file) = output_file {
+        fs::write(output_file, data)?;
+    } else {
+        io::stdout().write_all(data)?;
+    }
+    Ok(())
+}
+
+fn read_source(input_file: Option<&PathBuf>) -> Result<MessageSource, Box<dyn Error>> {
+    if let Some(input_file) = input_file {
         let input_file = fs::File::open(input_file)?;
         let mmap = unsafe { memmap::MmapOptions::new().map(&input_file)? };
-        MessageSource::File(mmap)
+        Ok(MessageSource::File(mmap))
     } else {
         let mut data = Vec::new();
         io::stdin().read_to_end(&mut data)?;
-        MessageSource::Stdin(data)
-    };
+        Ok(MessageSource::Stdin(data))
+    }
+}
 
-    let store = if let Some(ref path) = args.pfx_file {
-        let pfx_data = fs::read(path)?;
-        CertStore::from_pkcs12(
-            &pfx_data,
-            args.pin.as_ref().map(AsRef::as_ref).unwrap_or(""),
-        )?
-    } else {
-        let store_type = args.store_type.unwrap_or(CertStoreType::CurrentUser);
-        CertStore::open(store_type, "my")?
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: AppParams = AppParams::parse();
+
+    env_logger::init();
+
+    let open_store = |args: &AppParams| -> Result<CertStore, Box<dyn Error>> {
+        if let Some(ref path) = args.pfx_file {
+            let pfx_data = fs::read(path)?;
+            Ok(CertStore::from_pkcs12(
+                &pfx_data,
+                args.pin.as_ref().map(AsRef::as_ref).unwrap_or(""),
+            )?)
+        } else {
+            let store_type = args.store_type.unwrap_or(CertStoreType::CurrentUser);
+            Ok(CertStore::open(store_type, "my")?)
+        }
     };
 
     match args.command {
         CmsCommand::Encode(ref cmd) => {
-            let mut signers = store.find_cert_by_subject_str(&cmd.signer)?;
+            let store = open_store(&args)?;
+            let source = read_source(args.input_file.as_ref())?;
+            let signer = acquire_signer(
+                &store,
+                &cmd.signer,
+                args.silent,
+                args.pin.as_deref(),
+                args.pfx_file.is_some(),
+            )?;
+            let recipients = acquire_recipients(&store, &cmd.recipients)?;
+
+            let content = CmsContent::builder()
+                .signer(signer)
+                .recipients(recipients)
+                .digest_algorithm(cmd.digest)
+                .pss(cmd.pss)
+                .build();
+
+            let payload = if args.compress {
+                compress::pack(&source)?
+            } else {
+                source.to_vec()
+            };
+            let data = content.sign_and_encrypt(&payload)?;
+            write_output(&data, args.armor, args.output_file)?;
+        }
+        CmsCommand::Sign(ref cmd) => {
+            let store = open_store(&args)?;
+            let source = read_source(args.input_file.as_ref())?;
+            let signer = acquire_signer(
+                &store,
+                &cmd.signer,
+                args.silent,
+                args.pin.as_deref(),
+                args.pfx_file.is_some(),
+            )?;
+
+            let content = CmsContent::builder()
+                .signer(signer)
+                .digest_algorithm(cmd.digest)
+                .pss(cmd.pss)
+                .build();
+
+            let data = if cmd.detached {
+                content.sign_detached(&source)?
+            } else {
+                content.sign(&source)?
+            };
+            write_output(&data, args.armor, args.output_file)?;
+        }
+        CmsCommand::Encrypt(ref cmd) => {
+            let store = open_store(&args)?;
+            let source = read_source(args.input_file.as_ref())?;
+            let recipients = acquire_recipients(&store, &cmd.recipients)?;
 
-            if let Some(signer) = get_cert_with_key(&mut signers, args.silent) {
-                debug!("Acquired signer certificate for {}", cmd.signer);
+            let content = CmsContent::builder().recipients(recipients).build();
 
-                let mut recipients = Vec::new();
-                for rcpt in &cmd.recipients {
-                    recipients.extend(store.find_cert_by_subject_str(rcpt)?.into_iter());
+            let payload = if args.compress {
+                compress::pack(&source)?
+            } else {
+                source.to_vec()
+            };
+            let data = content.encrypt(&payload)?;
+            write_output(&data, args.armor, args.output_file)?;
+        }
+        CmsCommand::Decode(ref cmd) => {
+            let store = open_store(&args)?;
+            let source = read_source(args.input_file.as_ref())?;
+            let der = pem::maybe_dearmor(&source)?;
+
+            if let Some(ref content_file) = cmd.content_file {
+                let content_data = fs::read(content_file)?;
+                CmsContent::verify_detached(&store, &content_data, &der)?;
+                debug!("Detached signature verified against {:?}", content_file);
+                write_output(&content_data, false, args.output_file)?;
+            } else {
+                let info = inspect::inspect(&der)?;
+                let is_enveloped = info.is_enveloped;
+                let is_signed = !info.signers.is_empty() || info.encrypts_signed_data;
+
+                if is_enveloped {
+                    let recipient = cmd.recipient.as_ref().ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::Other,
+                            "Recipient certificate ID is required to decrypt an enveloped message",
+                        )
+                    })?;
+                    acquire_recipient(
+                        &store,
+                        recipient,
+                        args.silent,
+                        args.pin.as_deref(),
+                        args.pfx_file.is_some(),
+                    )?;
                 }
-                debug!("Acquired {} recipient certificate(s)", recipients.len());
-
-                let key = signer.key().unwrap();
-                let key_prov = key.get_provider_name()?;
-                let key_name = key.get_name()?;
-                debug!("Acquired private key: {}: {}", key_prov, key_name);
 
-                if args.pfx_file.is_none() {
-                    if let Some(pin) = args.pin {
-                        key.set_pin(&pin)?;
-                        debug!("Pin code set");
+                let data = match (is_enveloped, is_signed) {
+                    (true, true) => CmsContent::decrypt_and_verify(&store, &der)?,
+                    (true, false) => CmsContent::decrypt(&store, &der)?,
+                    (false, true) => CmsContent::verify(&der)?,
+                    (false, false) => {
+                        return Err(Box::new(io::Error::new(
+                            io::ErrorKind::Other,
+                            "Input is neither a signed nor an enveloped CMS message",
+                        )));
                     }
-                }
-
-                let content = CmsContent::builder()
-                    .signer(signer)
-                    .recipients(recipients)
-                    .build();
+                };
 
-                let data = content.sign_and_encrypt(&source)?;
-
-                if let Some(output_file) = args.output_file {
-                    fs::write(output_file, &data)?;
-                } else {
-                    io::stdout().write_all(&data)?;
-                }
-            } else {
-                return Err(Box::new(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Cannot find signer certificate for {}", cmd.signer),
-                )));
+                let data = compress::maybe_unpack(&data)?;
+                write_output(&data, false, args.output_file)?;
             }
         }
-        CmsCommand::Decode(ref cmd) => {
-            let mut recipients = store.find_cert_by_subject_str(&cmd.recipient)?;
-            if let Some(cert) = get_cert_with_key(&mut recipients, args.silent) {
-                debug!("Acquired recipient certificate for {}", cmd.recipient);
-
-                let key = cert.key().unwrap();
-                let key_prov = key.get_provider_name()?;
-                let key_name = key.get_name()?;
-                debug!("Acquired private key: {}: {}", key_prov, key_name);
-
-                if args.pfx_file.is_none() {
-                    if let Some(pin) = args.pin {
-                        key.set_pin(&pin)?;
-                        debug!("Pin code set");
+        CmsCommand::Inspect => {
+            let source = read_source(args.input_file.as_ref())?;
+            let der = pem::maybe_dearmor(&source)?;
+            let info = inspect::inspect(&der)?;
+
+            println!("Content type: {}", info.content_type);
+
+            if info.is_enveloped {
+                if !info.recipients.is_empty() {
+                    println!("Recipients:");
+                    for r in &info.recipients {
+                        println!("  {}", r.id);
                     }
                 }
+                println!(
+                    "Content encryption algorithm: {}",
+                    info.content_encryption_algorithm
+                );
+            }
 
-                let data = CmsContent::decrypt_and_verify(&store, &source)?;
-
-                if let Some(output_file) = args.output_file {
-                    fs::write(output_file, &data)?;
-                } else {
-                    io::stdout().write_all(&data)?;
+            if !info.signers.is_empty() {
+                println!("Signers:");
+                for s in &info.signers {
+                    println!("  {} digest={}", s.subject, s.digest_algorithm);
                 }
-            } else {
-                return Err(Box::new(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Cannot find recipient certificate for {}", cmd.recipient),
-                )));
+            }
+        }
+        CmsCommand::List(ref cmd) => {
+            let store = open_store(&args)?;
+            for mut cert in store.certs()? {
+                let key_label = if !cmd.keys {
+                    String::from("unknown (pass --keys to probe)")
+                } else if cert.acquire_key(true).is_ok() {
+                    cert.key()
+                        .map(|key| {
+                            let prov = key.get_provider_name().unwrap_or_default();
+                            let name = key.get_name().unwrap_or_default();
+                            format!("{}: {}", prov, name)
+                        })
+                        .unwrap_or_default()
+                } else {
+                    String::from("none")
+                };
+
+                println!("Subject:     {}", cert.subject());
+                println!("Issuer:      {}", cert.issuer());
+                println!("Serial:      {}", cert.serial());
+                println!("Valid:       {} - {}", cert.not_before(), cert.not_after());
+                println!(
+                    "Thumbprint:  {}",
+                    hex::encode(certid::thumbprint(&cert, 32))
+                );
+                println!("Private key: {}", key_label);
+                println!();
             }
         }
     }
@@ -0,0 +1,52 @@
+use std::error::Error;
+
+use pem::Pem;
+
+const LABEL: &str = "CMS";
+
+pub fn armor(der: &[u8]) -> String {
+    pem::encode(&Pem::new(LABEL, der.to_vec()))
+}
+
+pub fn maybe_dearmor(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if data.starts_with(b"-----BEGIN") {
+        Ok(pem::parse(data)?.into_contents())
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armor_dearmor_round_trip() {
+        let der = b"not actually DER, just some bytes".to_vec();
+        let armored = armor(&der);
+        assert_eq!(maybe_dearmor(armored.as_bytes()).unwrap(), der);
+    }
+
+    #[test]
+    fn maybe_dearmor_passes_through_non_pem_data() {
+        let der = b"\x30\x82\x01\x00not PEM armored";
+        assert_eq!(maybe_dearmor(der).unwrap(), der.to_vec());
+    }
+
+    #[test]
+    fn armor_uses_cms_label() {
+        let armored = armor(b"data");
+        assert!(armored.starts_with("-----BEGIN CMS-----"));
+        assert!(armored.trim_end().ends_with("-----END CMS-----"));
+    }
+
+    #[test]
+    fn armor_wraps_body_lines_at_64_chars() {
+        let armored = armor(&vec![0x42; 200]);
+        for line in armored.lines() {
+            if !line.starts_with("-----") {
+                assert!(line.len() <= 64, "line longer than 64 chars: {:?}", line);
+            }
+        }
+    }
+}